@@ -1,29 +1,288 @@
+use async_trait::async_trait;
 use libp2p::{
     core::upgrade,
     floodsub::{Floodsub, FloodsubEvent, Topic},
-    futures::StreamExt,
+    futures::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, StreamExt},
     identity,
     mdns::{Mdns, MdnsEvent},
     mplex,
+    multiaddr::Protocol,
     noise::{Keypair, NoiseConfig, X25519Spec},
-    swarm::{NetworkBehaviourEventProcess, Swarm, SwarmBuilder},
+    request_response::{
+        ProtocolName, ProtocolSupport, RequestResponse, RequestResponseCodec,
+        RequestResponseEvent, RequestResponseMessage, ResponseChannel,
+    },
+    swarm::{behaviour::toggle::Toggle, NetworkBehaviourEventProcess, Swarm, SwarmBuilder},
     tcp::TokioTcpConfig,
-    NetworkBehaviour, PeerId, Transport,
+    Multiaddr, NetworkBehaviour, PeerId, Transport,
 };
 use log::{error, info};
 use once_cell::sync::Lazy;
+use rand::Rng;
+use rusqlite::{params, Connection, ToSql};
 use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
-use tokio::{fs, io::AsyncBufReadExt, sync::mpsc};
+use std::sync::Mutex;
+use tokio::{io::AsyncBufReadExt, sync::mpsc};
 
-const STORAGE_FILE_PATH: &str = "./books.json";
+const STORAGE_DB_PATH: &str = "./books.db";
+const IDENTITY_FILE_PATH: &str = "./identity.key";
+const LIBRARY_KEY_FILE_PATH: &str = "./library.key";
+const PAIRED_PEERS_FILE_PATH: &str = "./paired_peers.json";
+const PEER_ACL_FILE_PATH: &str = "./peer_acl.json";
+const DISPLAY_NAME: &str = "anonymous";
 
 type Result<T> = std::result::Result<T, Box<dyn std::error::Error + Send + Sync + 'static>>;
 type Books = Vec<Book>;
+type PairedPeers = Vec<PairedPeer>;
 
-static KEYS: Lazy<identity::Keypair> = Lazy::new(|| identity::Keypair::generate_ed25519());
+static KEYS: Lazy<identity::Keypair> = Lazy::new(|| load_or_generate_keypair(IDENTITY_FILE_PATH));
 static PEER_ID: Lazy<PeerId> = Lazy::new(|| PeerId::from(KEYS.public()));
+static LIBRARY_KEYS: Lazy<identity::Keypair> =
+    Lazy::new(|| load_or_generate_keypair(LIBRARY_KEY_FILE_PATH));
+static LIBRARY_ID: Lazy<PeerId> = Lazy::new(|| PeerId::from(LIBRARY_KEYS.public()));
 static TOPIC: Lazy<Topic> = Lazy::new(|| Topic::new("books"));
+static PENDING_PAIRING_CODE: Lazy<Mutex<Option<String>>> = Lazy::new(|| Mutex::new(None));
+static CONFIG: Lazy<AppConfig> = Lazy::new(parse_config);
+static PEER_ACL: Lazy<Mutex<PeerAcl>> = Lazy::new(|| Mutex::new(load_peer_acl()));
+static PAIRED_PEERS: Lazy<Mutex<PairedPeers>> = Lazy::new(|| Mutex::new(load_paired_peers()));
+static BOOK_STORE: Lazy<BookStore> =
+    Lazy::new(|| BookStore::open(STORAGE_DB_PATH).expect("can open book store"));
+
+/// SQLite-backed repository for book reviews.
+struct BookStore {
+    conn: Mutex<Connection>,
+}
+
+impl BookStore {
+    fn open(path: &str) -> rusqlite::Result<Self> {
+        let conn = Connection::open(path)?;
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS books (
+                id      INTEGER PRIMARY KEY AUTOINCREMENT,
+                title   TEXT NOT NULL,
+                genre   TEXT NOT NULL,
+                author  TEXT NOT NULL,
+                rating  INTEGER NOT NULL,
+                review  TEXT NOT NULL,
+                public  INTEGER NOT NULL DEFAULT 0
+            );
+            CREATE INDEX IF NOT EXISTS idx_books_public ON books(public);",
+        )?;
+        Ok(Self {
+            conn: Mutex::new(conn),
+        })
+    }
+
+    fn create(&self, title: &str, genre: &str, author: &str, rating: u8, review: &str) -> rusqlite::Result<Book> {
+        let conn = self.conn.lock().expect("lock not poisoned");
+        conn.execute(
+            "INSERT INTO books (title, genre, author, rating, review, public) VALUES (?1, ?2, ?3, ?4, ?5, 0)",
+            params![title, genre, author, rating, review],
+        )?;
+        Ok(Book {
+            id: conn.last_insert_rowid() as usize,
+            title: title.to_owned(),
+            genre: genre.to_owned(),
+            author: author.to_owned(),
+            rating,
+            review: review.to_owned(),
+            public: false,
+        })
+    }
+
+    fn delete(&self, id: usize) -> rusqlite::Result<bool> {
+        let conn = self.conn.lock().expect("lock not poisoned");
+        let affected = conn.execute("DELETE FROM books WHERE id = ?1", params![id as i64])?;
+        Ok(affected > 0)
+    }
+
+    fn publish(&self, id: usize) -> rusqlite::Result<()> {
+        let conn = self.conn.lock().expect("lock not poisoned");
+        conn.execute(
+            "UPDATE books SET public = 1 WHERE id = ?1",
+            params![id as i64],
+        )?;
+        Ok(())
+    }
+
+    fn list_all(&self) -> rusqlite::Result<Books> {
+        self.query(
+            "SELECT id, title, genre, author, rating, review, public FROM books ORDER BY id",
+            &[],
+        )
+    }
+
+    fn list_public(&self) -> rusqlite::Result<Books> {
+        self.query(
+            "SELECT id, title, genre, author, rating, review, public FROM books WHERE public = 1 ORDER BY id",
+            &[],
+        )
+    }
+
+    /// Filters by genre/author (exact match), minimum rating, and/or a title/review substring.
+    fn search(
+        &self,
+        genre: Option<&str>,
+        author: Option<&str>,
+        min_rating: Option<u8>,
+        text: Option<&str>,
+        public_only: bool,
+    ) -> rusqlite::Result<Books> {
+        let mut sql = String::from(
+            "SELECT id, title, genre, author, rating, review, public FROM books WHERE 1 = 1",
+        );
+        let mut params: Vec<Box<dyn ToSql>> = Vec::new();
+        if public_only {
+            sql.push_str(" AND public = 1");
+        }
+        if let Some(genre) = genre {
+            sql.push_str(" AND genre = ?");
+            params.push(Box::new(genre.to_owned()));
+        }
+        if let Some(author) = author {
+            sql.push_str(" AND author = ?");
+            params.push(Box::new(author.to_owned()));
+        }
+        if let Some(min_rating) = min_rating {
+            sql.push_str(" AND rating >= ?");
+            params.push(Box::new(min_rating));
+        }
+        if let Some(text) = text {
+            sql.push_str(" AND (title LIKE ? ESCAPE '\\' OR review LIKE ? ESCAPE '\\')");
+            let pattern = format!("%{}%", escape_like_pattern(text));
+            params.push(Box::new(pattern.clone()));
+            params.push(Box::new(pattern));
+        }
+        sql.push_str(" ORDER BY id");
+
+        let param_refs: Vec<&dyn ToSql> = params.iter().map(|p| p.as_ref()).collect();
+        self.query(&sql, param_refs.as_slice())
+    }
+
+    fn query(&self, sql: &str, params: &[&dyn ToSql]) -> rusqlite::Result<Books> {
+        let conn = self.conn.lock().expect("lock not poisoned");
+        let mut stmt = conn.prepare(sql)?;
+        let rows = stmt.query_map(params, |row| {
+            Ok(Book {
+                id: row.get::<_, i64>(0)? as usize,
+                title: row.get(1)?,
+                genre: row.get(2)?,
+                author: row.get(3)?,
+                rating: row.get(4)?,
+                review: row.get(5)?,
+                public: row.get(6)?,
+            })
+        })?;
+        rows.collect()
+    }
+}
+
+/// Escapes `\`, `%`, and `_` so a user-supplied substring is matched literally by `LIKE`.
+fn escape_like_pattern(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+/// A persisted set of blocked and explicitly-trusted peers.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct PeerAcl {
+    blocked: HashSet<String>,
+    trusted: HashSet<String>,
+    allowlist_only: bool,
+}
+
+fn load_peer_acl() -> PeerAcl {
+    match std::fs::read(PEER_ACL_FILE_PATH) {
+        Ok(content) => serde_json::from_slice(&content).unwrap_or_default(),
+        Err(_) => PeerAcl::default(),
+    }
+}
+
+fn persist_peer_acl(acl: &PeerAcl) {
+    match serde_json::to_string(acl) {
+        Ok(json) => {
+            if let Err(e) = std::fs::write(PEER_ACL_FILE_PATH, json) {
+                error!("error persisting peer acl: {}", e);
+            }
+        }
+        Err(e) => error!("error serializing peer acl: {}", e),
+    }
+}
+
+/// Whether `peer_id` should be answered: not blocked, and - in allowlist-only mode - trusted.
+fn is_allowed_peer(peer_id: &str) -> bool {
+    let acl = PEER_ACL.lock().expect("lock not poisoned");
+    if acl.blocked.contains(peer_id) {
+        return false;
+    }
+    !acl.allowlist_only || acl.trusted.contains(peer_id)
+}
+
+/// Startup configuration controlling peer discovery.
+struct AppConfig {
+    no_mdns: bool,
+    bootstrap_peers: Vec<Multiaddr>,
+}
+
+/// Reads `--no-mdns`/`NO_MDNS` and `--bootstrap <multiaddr>`/`BOOTSTRAP_PEERS` from args and env.
+fn parse_config() -> AppConfig {
+    let args: Vec<String> = std::env::args().collect();
+    let no_mdns = args.iter().any(|a| a == "--no-mdns") || std::env::var("NO_MDNS").is_ok();
+
+    let mut bootstrap_peers = Vec::new();
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        if arg == "--bootstrap" {
+            if let Some(addr) = iter.next() {
+                match addr.parse() {
+                    Ok(addr) => bootstrap_peers.push(addr),
+                    Err(e) => error!("invalid bootstrap multiaddr {}: {}", addr, e),
+                }
+            }
+        }
+    }
+    if let Ok(env_peers) = std::env::var("BOOTSTRAP_PEERS") {
+        for addr in env_peers.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+            match addr.parse() {
+                Ok(addr) => bootstrap_peers.push(addr),
+                Err(e) => error!("invalid bootstrap multiaddr {}: {}", addr, e),
+            }
+        }
+    }
+
+    AppConfig {
+        no_mdns,
+        bootstrap_peers,
+    }
+}
+
+/// Pulls the `/p2p/<peer-id>` suffix out of a dialable multiaddr, if present.
+fn peer_id_from_multiaddr(addr: &Multiaddr) -> Option<PeerId> {
+    addr.iter().find_map(|p| match p {
+        Protocol::P2p(hash) => PeerId::from_multihash(hash).ok(),
+        _ => None,
+    })
+}
+
+/// Loads an ed25519 keypair from `path`, generating and persisting a fresh one on first run.
+fn load_or_generate_keypair(path: &str) -> identity::Keypair {
+    match std::fs::read(path) {
+        Ok(mut bytes) => {
+            let keypair =
+                identity::ed25519::Keypair::decode(&mut bytes).expect("identity file is corrupted");
+            identity::Keypair::Ed25519(keypair)
+        }
+        Err(_) => {
+            let keypair = identity::Keypair::generate_ed25519();
+            if let identity::Keypair::Ed25519(ref ed25519_keypair) = keypair {
+                std::fs::write(path, ed25519_keypair.encode()).expect("can persist keypair");
+            }
+            keypair
+        }
+    }
+}
 
 #[derive(Debug, Serialize, Deserialize)]
 struct Book {
@@ -31,7 +290,7 @@ struct Book {
     title: String,
     genre: String,
     author: String,
-    rating: String,
+    rating: u8,
     review: String,
     public: bool,
 }
@@ -40,6 +299,12 @@ struct Book {
 enum ListMode {
     ALL,
     One(String),
+    Search {
+        genre: Option<String>,
+        author: Option<String>,
+        min_rating: Option<u8>,
+        text: Option<String>,
+    },
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -54,23 +319,185 @@ struct ListResponse {
     receiver: String,
 }
 
+/// Identifies a node to a peer it is pairing with.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct NodeInformation {
+    peer_id: String,
+    library_id: String,
+    display_name: String,
+    public_key: Vec<u8>,
+}
+
+/// `NodeInformation` signed with the identity key embedded in `info.public_key`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SignedNodeInformation {
+    info: NodeInformation,
+    signature: Vec<u8>,
+}
+
+fn signed_node_information() -> SignedNodeInformation {
+    let info = NodeInformation {
+        peer_id: PEER_ID.to_string(),
+        library_id: LIBRARY_ID.to_string(),
+        display_name: DISPLAY_NAME.to_owned(),
+        public_key: KEYS.public().into_protobuf_encoding(),
+    };
+    let payload = serde_json::to_vec(&info).expect("can serialize node information");
+    let signature = KEYS.sign(&payload).expect("can sign node information");
+    SignedNodeInformation { info, signature }
+}
+
+/// Checks `signed.info.public_key` hashes to `signed.info.peer_id` and the signature verifies.
+fn verify_node_information(signed: &SignedNodeInformation) -> bool {
+    let public_key = match identity::PublicKey::from_protobuf_encoding(&signed.info.public_key) {
+        Ok(key) => key,
+        Err(_) => return false,
+    };
+    if PeerId::from(public_key.clone()).to_string() != signed.info.peer_id {
+        return false;
+    }
+    match serde_json::to_vec(&signed.info) {
+        Ok(payload) => public_key.verify(&payload, &signed.signature),
+        Err(_) => false,
+    }
+}
+
+/// A pairing code presented to another node, together with a signed `NodeInformation`.
+#[derive(Debug, Serialize, Deserialize)]
+struct PairRequest {
+    code: String,
+    info: SignedNodeInformation,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct PairedPeer {
+    peer_id: String,
+    library_id: String,
+    display_name: String,
+}
+
+/// Request body for the direct `request_response` exchange: a list query or a pairing handshake.
+#[derive(Debug, Serialize, Deserialize)]
+enum ExchangeRequest {
+    List(ListRequest),
+    Pair(PairRequest),
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+enum ExchangeResponse {
+    List(ListResponse),
+    Pair(SignedNodeInformation),
+}
+
+const BOOK_EXCHANGE_PROTOCOL: &[u8] = b"/book-exchange/1.0.0";
+
+#[derive(Debug, Clone, Default)]
+struct BookExchangeProtocol;
+
+impl ProtocolName for BookExchangeProtocol {
+    fn protocol_name(&self) -> &[u8] {
+        BOOK_EXCHANGE_PROTOCOL
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct BookExchangeCodec;
+
+#[async_trait]
+impl RequestResponseCodec for BookExchangeCodec {
+    type Protocol = BookExchangeProtocol;
+    type Request = ExchangeRequest;
+    type Response = ExchangeResponse;
+
+    async fn read_request<T>(
+        &mut self,
+        _: &BookExchangeProtocol,
+        io: &mut T,
+    ) -> std::io::Result<ExchangeRequest>
+    where
+        T: AsyncRead + Unpin + Send,
+    {
+        let mut buf = Vec::new();
+        io.read_to_end(&mut buf).await?;
+        serde_json::from_slice(&buf).map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+    }
+
+    async fn read_response<T>(
+        &mut self,
+        _: &BookExchangeProtocol,
+        io: &mut T,
+    ) -> std::io::Result<ExchangeResponse>
+    where
+        T: AsyncRead + Unpin + Send,
+    {
+        let mut buf = Vec::new();
+        io.read_to_end(&mut buf).await?;
+        serde_json::from_slice(&buf).map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+    }
+
+    async fn write_request<T>(
+        &mut self,
+        _: &BookExchangeProtocol,
+        io: &mut T,
+        req: ExchangeRequest,
+    ) -> std::io::Result<()>
+    where
+        T: AsyncWrite + Unpin + Send,
+    {
+        let json = serde_json::to_vec(&req)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        io.write_all(&json).await?;
+        io.close().await
+    }
+
+    async fn write_response<T>(
+        &mut self,
+        _: &BookExchangeProtocol,
+        io: &mut T,
+        resp: ExchangeResponse,
+    ) -> std::io::Result<()>
+    where
+        T: AsyncWrite + Unpin + Send,
+    {
+        let json = serde_json::to_vec(&resp)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        io.write_all(&json).await?;
+        io.close().await
+    }
+}
+
+/// Where a `ListResponse` should go: floodsub broadcast or a direct `request_response` channel.
+enum Destination {
+    Broadcast(String),
+    Direct(ResponseChannel<ExchangeResponse>),
+}
+
+enum OutboundResponse {
+    Broadcast(ListResponse),
+    Direct(ResponseChannel<ExchangeResponse>, ExchangeResponse),
+}
+
 enum EventType {
-    Response(ListResponse),
+    Response(OutboundResponse),
     Input(String),
 }
 
 #[derive(NetworkBehaviour)]
 struct BookBehaviour {
     floodsub: Floodsub,
-    mdns: Mdns,
+    mdns: Toggle<Mdns>,
+    request_response: RequestResponse<BookExchangeCodec>,
     #[behaviour(ignore)]
-    response_sender: mpsc::UnboundedSender<ListResponse>,
+    response_sender: mpsc::UnboundedSender<OutboundResponse>,
 }
 
 impl NetworkBehaviourEventProcess<FloodsubEvent> for BookBehaviour {
     fn inject_event(&mut self, event: FloodsubEvent) {
         match event {
             FloodsubEvent::Message(msg) => {
+                if PEER_ACL.lock().expect("lock not poisoned").blocked.contains(&msg.source.to_string()) {
+                    return;
+                }
                 if let Ok(resp) = serde_json::from_slice::<ListResponse>(&msg.data) {
                     if resp.receiver == PEER_ID.to_string() {
                         info!("Response from {}:", msg.source);
@@ -78,22 +505,16 @@ impl NetworkBehaviourEventProcess<FloodsubEvent> for BookBehaviour {
                     }
                 } else if let Ok(req) = serde_json::from_slice::<ListRequest>(&msg.data) {
                     match req.mode {
-                        ListMode::ALL => {
-                            info!("Received ALL req: {:?} from {:?}", req, msg.source);
-                            respond_with_public_books(
+                        ListMode::One(_) => (), // targeted queries now go over request_response
+                        _ => {
+                            info!("Received broadcast req: {:?} from {:?}", req, msg.source);
+                            respond_to_request(
                                 self.response_sender.clone(),
+                                Destination::Broadcast(msg.source.to_string()),
                                 msg.source.to_string(),
+                                req.mode,
                             );
                         }
-                        ListMode::One(ref peer_id) => {
-                            if peer_id == &PEER_ID.to_string() {
-                                info!("Received req: {:?} from {:?}", req, msg.source);
-                                respond_with_public_books(
-                                    self.response_sender.clone(),
-                                    msg.source.to_string(),
-                                );
-                            }
-                        }
                     }
                 }
             }
@@ -102,20 +523,192 @@ impl NetworkBehaviourEventProcess<FloodsubEvent> for BookBehaviour {
     }
 }
 
-fn respond_with_public_books(sender: mpsc::UnboundedSender<ListResponse>, receiver: String) {
+impl NetworkBehaviourEventProcess<RequestResponseEvent<ExchangeRequest, ExchangeResponse>> for BookBehaviour {
+    fn inject_event(&mut self, event: RequestResponseEvent<ExchangeRequest, ExchangeResponse>) {
+        match event {
+            RequestResponseEvent::Message { peer, message } => match message {
+                RequestResponseMessage::Request { request, channel, .. } => match request {
+                    ExchangeRequest::List(req) => {
+                        info!("Received direct req: {:?} from {:?}", req, peer);
+                        respond_to_request(
+                            self.response_sender.clone(),
+                            Destination::Direct(channel),
+                            peer.to_string(),
+                            req.mode,
+                        );
+                    }
+                    ExchangeRequest::Pair(pair_req) => {
+                        handle_pair_request(peer, pair_req, channel, self.response_sender.clone());
+                    }
+                },
+                RequestResponseMessage::Response { response, .. } => match response {
+                    ExchangeResponse::List(resp) => {
+                        info!("Response from {}:", peer);
+                        resp.data.iter().for_each(|r| info!("{:?}", r));
+                    }
+                    ExchangeResponse::Pair(signed_info) => handle_pair_response(peer, signed_info),
+                },
+            },
+            RequestResponseEvent::OutboundFailure { peer, error, .. } => {
+                error!("request to {} failed: {:?}", peer, error);
+            }
+            RequestResponseEvent::InboundFailure { peer, error, .. } => {
+                error!("request from {} failed: {:?}", peer, error);
+            }
+            RequestResponseEvent::ResponseSent { .. } => (),
+        }
+    }
+}
+
+/// Verifies the sender isn't blocked and the pairing code/signature check out, then pairs.
+fn handle_pair_request(
+    peer: PeerId,
+    pair_req: PairRequest,
+    channel: ResponseChannel<ExchangeResponse>,
+    sender: mpsc::UnboundedSender<OutboundResponse>,
+) {
+    if !is_allowed_peer(&peer.to_string()) {
+        info!("refusing pairing request from blocked/untrusted peer {}", peer);
+        return;
+    }
+    if !verify_node_information(&pair_req.info) || pair_req.info.info.peer_id != peer.to_string() {
+        info!("rejecting pairing request with invalid signature from {}", peer);
+        return;
+    }
+    let expected = PENDING_PAIRING_CODE.lock().expect("lock not poisoned").clone();
+    if expected.as_deref() != Some(pair_req.code.as_str()) {
+        return;
+    }
+    info!("Pairing request accepted from {}", pair_req.info.info.peer_id);
+    let their_info = pair_req.info.info.clone();
+    tokio::spawn(async move {
+        if let Err(e) = store_paired_peer(their_info).await {
+            error!("error persisting paired peer: {}", e);
+        }
+        if sender
+            .send(OutboundResponse::Direct(
+                channel,
+                ExchangeResponse::Pair(signed_node_information()),
+            ))
+            .is_err()
+        {
+            error!("error sending pairing response via channel");
+        }
+    });
+    *PENDING_PAIRING_CODE.lock().expect("lock not poisoned") = None;
+}
+
+/// Verifies the reply came from the peer we dialed, then stores it as paired.
+fn handle_pair_response(peer: PeerId, signed_info: SignedNodeInformation) {
+    if !verify_node_information(&signed_info) || signed_info.info.peer_id != peer.to_string() {
+        info!("rejecting pairing response with invalid signature from {}", peer);
+        return;
+    }
+    let info = signed_info.info;
+    info!("Paired with {}", info.peer_id);
+    tokio::spawn(async move {
+        if let Err(e) = store_paired_peer(info).await {
+            error!("error persisting paired peer: {}", e);
+        }
+    });
+}
+
+async fn store_paired_peer(info: NodeInformation) -> Result<()> {
+    let mut peers = PAIRED_PEERS.lock().expect("lock not poisoned");
+    if !peers.iter().any(|p| p.peer_id == info.peer_id) {
+        peers.push(PairedPeer {
+            peer_id: info.peer_id,
+            library_id: info.library_id,
+            display_name: info.display_name,
+        });
+        persist_paired_peers(&peers);
+    }
+    Ok(())
+}
+
+async fn is_paired_peer(peer_id: &str) -> bool {
+    PAIRED_PEERS
+        .lock()
+        .expect("lock not poisoned")
+        .iter()
+        .any(|p| p.peer_id == peer_id)
+}
+
+fn load_paired_peers() -> PairedPeers {
+    match std::fs::read(PAIRED_PEERS_FILE_PATH) {
+        Ok(content) => serde_json::from_slice(&content).unwrap_or_default(),
+        Err(_) => PairedPeers::default(),
+    }
+}
+
+fn persist_paired_peers(peers: &PairedPeers) {
+    match serde_json::to_string(peers) {
+        Ok(json) => {
+            if let Err(e) = std::fs::write(PAIRED_PEERS_FILE_PATH, json) {
+                error!("error persisting paired peers: {}", e);
+            }
+        }
+        Err(e) => error!("error serializing paired peers: {}", e),
+    }
+}
+
+/// Builds a short pairing code: a base58 fingerprint of our library's public key plus a nonce.
+fn generate_pairing_code() -> String {
+    let nonce: u32 = rand::thread_rng().gen();
+    let mut payload = LIBRARY_ID.to_bytes();
+    payload.extend_from_slice(&nonce.to_be_bytes());
+    bs58::encode(payload).into_string()
+}
+
+fn respond_to_request(
+    sender: mpsc::UnboundedSender<OutboundResponse>,
+    destination: Destination,
+    requester: String,
+    mode: ListMode,
+) {
+    if !is_allowed_peer(&requester) {
+        info!("refusing to answer blocked/untrusted peer {}", requester);
+        return;
+    }
     tokio::spawn(async move {
-        match read_local_books().await {
-            Ok(books) => {
-                let resp = ListResponse {
-                    mode: ListMode::ALL,
-                    receiver,
-                    data: books.into_iter().filter(|r| r.public).collect(),
+        let paired = is_paired_peer(&requester).await;
+        let books = match mode {
+            ListMode::Search {
+                genre,
+                author,
+                min_rating,
+                text,
+            } => search_books(genre, author, min_rating, text, !paired).await,
+            ListMode::ALL | ListMode::One(_) => {
+                if paired {
+                    read_local_books().await
+                } else {
+                    read_public_books().await
+                }
+            }
+        };
+        match books {
+            Ok(data) => {
+                let outbound = match destination {
+                    Destination::Broadcast(receiver) => OutboundResponse::Broadcast(ListResponse {
+                        mode: ListMode::ALL,
+                        receiver,
+                        data,
+                    }),
+                    Destination::Direct(channel) => OutboundResponse::Direct(
+                        channel,
+                        ExchangeResponse::List(ListResponse {
+                            mode: ListMode::ALL,
+                            receiver: requester,
+                            data,
+                        }),
+                    ),
                 };
-                if let Err(e) = sender.send(resp) {
-                    error!("error sending response via channel, {}", e);
+                if sender.send(outbound).is_err() {
+                    error!("error sending response via channel");
                 }
             }
-            Err(e) => error!("error fetching local books to answer ALL request, {}", e),
+            Err(e) => error!("error fetching local books to answer request, {}", e),
         }
     });
 }
@@ -130,7 +723,8 @@ impl NetworkBehaviourEventProcess<MdnsEvent> for BookBehaviour {
             }
             MdnsEvent::Expired(expired_list) => {
                 for (peer, _addr) in expired_list {
-                    if !self.mdns.has_node(&peer) {
+                    let still_known = self.mdns.as_ref().map_or(false, |m| m.has_node(&peer));
+                    if !still_known {
                         self.floodsub.remove_node_from_partial_view(&peer);
                     }
                 }
@@ -139,22 +733,8 @@ impl NetworkBehaviourEventProcess<MdnsEvent> for BookBehaviour {
     }
 }
 
-async fn create_new_book(title: &str, genre: &str, author: &str, rating: &str, review: &str) -> Result<()> {
-    let mut local_books = read_local_books().await?;
-    let new_id = match local_books.iter().max_by_key(|r| r.id) {
-        Some(v) => v.id + 1,
-        None => 0,
-    };
-    local_books.push(Book {
-        id: new_id,
-        title: title.to_owned(),
-        genre: genre.to_owned(),
-        author: genre.to_owned(),
-        rating: rating.to_owned(),
-        review: review.to_owned(),
-        public: false,
-    });
-    write_local_books(&local_books).await?;
+async fn create_new_book(title: &str, genre: &str, author: &str, rating: u8, review: &str) -> Result<()> {
+    BOOK_STORE.create(title, genre, author, rating, review)?;
 
     info!("Created book review:");
     info!("Title: {}", title);
@@ -167,10 +747,7 @@ async fn create_new_book(title: &str, genre: &str, author: &str, rating: &str, r
 }
 
 async fn delete_book_review(id: usize) -> Result<()> {
-    let mut local_books = read_local_books().await?;
-    if let Some(index) = local_books.iter().position(|book| book.id == id) {
-        local_books.remove(index);
-        write_local_books(&local_books).await?;
+    if BOOK_STORE.delete(id)? {
         Ok(())
     } else {
         Err("Book review not found".into())
@@ -180,25 +757,32 @@ async fn delete_book_review(id: usize) -> Result<()> {
 
 
 async fn publish_book(id: usize) -> Result<()> {
-    let mut local_books = read_local_books().await?;
-    local_books
-        .iter_mut()
-        .filter(|r| r.id == id)
-        .for_each(|r| r.public = true);
-    write_local_books(&local_books).await?;
+    BOOK_STORE.publish(id)?;
     Ok(())
 }
 
 async fn read_local_books() -> Result<Books> {
-    let content = fs::read(STORAGE_FILE_PATH).await?;
-    let result = serde_json::from_slice(&content)?;
-    Ok(result)
+    Ok(BOOK_STORE.list_all()?)
 }
 
-async fn write_local_books(books: &Books) -> Result<()> {
-    let json = serde_json::to_string(&books)?;
-    fs::write(STORAGE_FILE_PATH, &json).await?;
-    Ok(())
+async fn read_public_books() -> Result<Books> {
+    Ok(BOOK_STORE.list_public()?)
+}
+
+async fn search_books(
+    genre: Option<String>,
+    author: Option<String>,
+    min_rating: Option<u8>,
+    text: Option<String>,
+    public_only: bool,
+) -> Result<Books> {
+    Ok(BOOK_STORE.search(
+        genre.as_deref(),
+        author.as_deref(),
+        min_rating,
+        text.as_deref(),
+        public_only,
+    )?)
 }
 
 #[tokio::main]
@@ -206,6 +790,7 @@ async fn main() {
     pretty_env_logger::init();
 
     info!("Peer Id: {}", PEER_ID.clone());
+    info!("Library Id: {}", LIBRARY_ID.clone());
     let (response_sender, mut response_rcv) = mpsc::unbounded_channel();
 
     let auth_keys = Keypair::<X25519Spec>::new()
@@ -218,11 +803,26 @@ async fn main() {
         .multiplex(mplex::MplexConfig::new())
         .boxed();
 
+    let mdns = if CONFIG.no_mdns {
+        None
+    } else {
+        Some(
+            Mdns::new(Default::default())
+                .await
+                .expect("can create mdns"),
+        )
+    };
+
+    let request_response = RequestResponse::new(
+        BookExchangeCodec,
+        std::iter::once((BookExchangeProtocol, ProtocolSupport::Full)),
+        Default::default(),
+    );
+
     let mut behaviour = BookBehaviour {
         floodsub: Floodsub::new(PEER_ID.clone()),
-        mdns: Mdns::new(Default::default())
-            .await
-            .expect("can create mdns"),
+        mdns: Toggle::from(mdns),
+        request_response,
         response_sender,
     };
 
@@ -244,6 +844,10 @@ async fn main() {
     )
     .expect("swarm can be started");
 
+    for addr in &CONFIG.bootstrap_peers {
+        dial_addr(&mut swarm, addr.clone());
+    }
+
     loop {
         let evt = {
             tokio::select! {
@@ -258,19 +862,40 @@ async fn main() {
 
         if let Some(event) = evt {
             match event {
-                EventType::Response(resp) => {
+                EventType::Response(OutboundResponse::Broadcast(resp)) => {
                     let json = serde_json::to_string(&resp).expect("can jsonify response");
                     swarm
                         .behaviour_mut()
                         .floodsub
                         .publish(TOPIC.clone(), json.as_bytes());
                 }
+                EventType::Response(OutboundResponse::Direct(channel, resp)) => {
+                    if swarm
+                        .behaviour_mut()
+                        .request_response
+                        .send_response(channel, resp)
+                        .is_err()
+                    {
+                        error!("error sending direct response, peer likely disconnected");
+                    }
+                }
                 EventType::Input(line) => match line.as_str() {
                     "list peers" => handle_list_peers(&mut swarm).await,
                     cmd if cmd.starts_with("list reviews") => handle_list_reviews(cmd, &mut swarm).await,
+                    cmd if cmd.starts_with("search reviews") => handle_search_reviews(cmd, &mut swarm).await,
                     cmd if cmd.starts_with("create review") => handle_create_book_review(cmd).await,
                     cmd if cmd.starts_with("publish review") => handle_publish_book_review(cmd).await,
                     cmd if cmd.starts_with("delete review") => handle_delete_book_review(cmd).await,
+                    "pair" => handle_pair(&mut swarm).await,
+                    cmd if cmd.starts_with("pair ") => handle_pair_code(cmd, &mut swarm).await,
+                    cmd if cmd.starts_with("dial ") => handle_dial(cmd, &mut swarm).await,
+                    cmd if cmd.starts_with("block ") => handle_block(cmd).await,
+                    cmd if cmd.starts_with("unblock ") => handle_unblock(cmd).await,
+                    cmd if cmd.starts_with("trust ") => handle_trust(cmd).await,
+                    cmd if cmd.starts_with("untrust ") => handle_untrust(cmd).await,
+                    "list blocked" => handle_list_blocked().await,
+                    "allowlist on" => handle_allowlist(true).await,
+                    "allowlist off" => handle_allowlist(false).await,
                     _ => error!("unknown command"),
                 },
             }
@@ -278,9 +903,42 @@ async fn main() {
     }
 }
 
+/// Dials `addr` and adds any `/p2p/<peer-id>` suffix to the floodsub partial view immediately.
+fn dial_addr(swarm: &mut Swarm<BookBehaviour>, addr: Multiaddr) {
+    let peer_id = peer_id_from_multiaddr(&addr);
+    match Swarm::dial_addr(swarm, addr.clone()) {
+        Ok(_) => {
+            info!("Dialing {}", addr);
+            if let Some(peer_id) = peer_id {
+                swarm.behaviour_mut().floodsub.add_node_to_partial_view(peer_id);
+            }
+        }
+        Err(e) => error!("error dialing {}: {}", addr, e),
+    }
+}
+
+async fn handle_dial(cmd: &str, swarm: &mut Swarm<BookBehaviour>) {
+    if let Some(addr) = cmd.strip_prefix("dial ") {
+        match addr.trim().parse() {
+            Ok(addr) => dial_addr(swarm, addr),
+            Err(e) => error!("invalid multiaddr {}: {}", addr.trim(), e),
+        }
+    }
+}
+
 async fn handle_list_peers(swarm: &mut Swarm<BookBehaviour>) {
     info!("Discovered Peers:");
-    let nodes = swarm.behaviour().mdns.discovered_nodes();
+    if CONFIG.no_mdns {
+        swarm.connected_peers().for_each(|p| info!("{}", p));
+        return;
+    }
+    let nodes = swarm
+        .behaviour()
+        .mdns
+        .as_ref()
+        .map(|m| m.discovered_nodes())
+        .into_iter()
+        .flatten();
     let mut unique_peers = HashSet::new();
     for peer in nodes {
         unique_peers.insert(peer);
@@ -301,16 +959,15 @@ async fn handle_list_reviews(cmd: &str, swarm: &mut Swarm<BookBehaviour>) {
                 .floodsub
                 .publish(TOPIC.clone(), json.as_bytes());
         }
-        Some(books_peer_id) => {
-            let req = ListRequest {
-                mode: ListMode::One(books_peer_id.to_owned()),
-            };
-            let json = serde_json::to_string(&req).expect("can jsonify request");
-            swarm
-                .behaviour_mut()
-                .floodsub
-                .publish(TOPIC.clone(), json.as_bytes());
-        }
+        Some(books_peer_id) => match books_peer_id.parse::<PeerId>() {
+            Ok(peer) => {
+                let req = ExchangeRequest::List(ListRequest {
+                    mode: ListMode::One(books_peer_id.to_owned()),
+                });
+                swarm.behaviour_mut().request_response.send_request(&peer, req);
+            }
+            Err(e) => error!("invalid peer id {}: {}", books_peer_id, e),
+        },
         None => {
             match read_local_books().await {
                 Ok(v) => {
@@ -323,6 +980,153 @@ async fn handle_list_reviews(cmd: &str, swarm: &mut Swarm<BookBehaviour>) {
     };
 }
 
+/// Parses `field:value` tokens (genre, author, min_rating, text) into a filter.
+fn parse_search_filters(
+    filters: &[&str],
+) -> (Option<String>, Option<String>, Option<u8>, Option<String>) {
+    let mut genre = None;
+    let mut author = None;
+    let mut min_rating = None;
+    let mut text = None;
+    for filter in filters {
+        match filter.split_once(':') {
+            Some(("genre", value)) => genre = Some(value.to_owned()),
+            Some(("author", value)) => author = Some(value.to_owned()),
+            Some(("min_rating", value)) => match value.parse::<u8>() {
+                Ok(v) => min_rating = Some(v),
+                Err(e) => error!("invalid min_rating {}: {}", value, e),
+            },
+            Some(("text", value)) => text = Some(value.to_owned()),
+            Some((field, _)) => error!("unknown search field: {}", field),
+            None => error!("expected field:value, got {}", filter),
+        }
+    }
+    (genre, author, min_rating, text)
+}
+
+/// Handles `search reviews [all|<peer_id>] <field>:<value> ...`.
+async fn handle_search_reviews(cmd: &str, swarm: &mut Swarm<BookBehaviour>) {
+    let rest = match cmd.strip_prefix("search reviews") {
+        Some(rest) => rest.trim(),
+        None => return,
+    };
+    let mut tokens = rest.split_whitespace().peekable();
+    let target = match tokens.peek() {
+        Some(first) if !first.contains(':') => tokens.next(),
+        _ => None,
+    };
+    let filters: Vec<&str> = tokens.collect();
+    let (genre, author, min_rating, text) = parse_search_filters(&filters);
+    let mode = ListMode::Search {
+        genre: genre.clone(),
+        author: author.clone(),
+        min_rating,
+        text: text.clone(),
+    };
+
+    match target {
+        Some("all") => {
+            let req = ListRequest { mode };
+            let json = serde_json::to_string(&req).expect("can jsonify request");
+            swarm
+                .behaviour_mut()
+                .floodsub
+                .publish(TOPIC.clone(), json.as_bytes());
+        }
+        Some(peer_id) => match peer_id.parse::<PeerId>() {
+            Ok(peer) => {
+                let req = ExchangeRequest::List(ListRequest { mode });
+                swarm.behaviour_mut().request_response.send_request(&peer, req);
+            }
+            Err(e) => error!("invalid peer id {}: {}", peer_id, e),
+        },
+        None => match search_books(genre, author, min_rating, text, false).await {
+            Ok(v) => {
+                info!("Matching books ({})", v.len());
+                v.iter().for_each(|r| info!("{:?}", r));
+            }
+            Err(e) => error!("error searching local books: {}", e),
+        },
+    };
+}
+
+/// Prints a pairing code derived from our library key.
+async fn handle_pair(_swarm: &mut Swarm<BookBehaviour>) {
+    let code = generate_pairing_code();
+    *PENDING_PAIRING_CODE.lock().expect("lock not poisoned") = Some(code.clone());
+    info!("Pairing code (share with the other peer): {}", code);
+}
+
+/// Handles `pair <peer_id> <code>`, sending the pairing handshake directly to that peer.
+async fn handle_pair_code(cmd: &str, swarm: &mut Swarm<BookBehaviour>) {
+    if let Some(rest) = cmd.strip_prefix("pair ") {
+        let mut parts = rest.trim().splitn(2, ' ');
+        match (parts.next(), parts.next()) {
+            (Some(peer_id), Some(code)) => match peer_id.parse::<PeerId>() {
+                Ok(peer) => {
+                    let req = ExchangeRequest::Pair(PairRequest {
+                        code: code.trim().to_owned(),
+                        info: signed_node_information(),
+                    });
+                    swarm.behaviour_mut().request_response.send_request(&peer, req);
+                }
+                Err(e) => error!("invalid peer id {}: {}", peer_id, e),
+            },
+            _ => info!("usage: pair <peer_id> <code>"),
+        }
+    }
+}
+
+async fn handle_block(cmd: &str) {
+    if let Some(peer_id) = cmd.strip_prefix("block ") {
+        let mut acl = PEER_ACL.lock().expect("lock not poisoned");
+        acl.blocked.insert(peer_id.trim().to_owned());
+        persist_peer_acl(&acl);
+        info!("Blocked peer: {}", peer_id.trim());
+    }
+}
+
+async fn handle_unblock(cmd: &str) {
+    if let Some(peer_id) = cmd.strip_prefix("unblock ") {
+        let mut acl = PEER_ACL.lock().expect("lock not poisoned");
+        acl.blocked.remove(peer_id.trim());
+        persist_peer_acl(&acl);
+        info!("Unblocked peer: {}", peer_id.trim());
+    }
+}
+
+async fn handle_trust(cmd: &str) {
+    if let Some(peer_id) = cmd.strip_prefix("trust ") {
+        let mut acl = PEER_ACL.lock().expect("lock not poisoned");
+        acl.trusted.insert(peer_id.trim().to_owned());
+        persist_peer_acl(&acl);
+        info!("Trusted peer: {}", peer_id.trim());
+    }
+}
+
+async fn handle_untrust(cmd: &str) {
+    if let Some(peer_id) = cmd.strip_prefix("untrust ") {
+        let mut acl = PEER_ACL.lock().expect("lock not poisoned");
+        acl.trusted.remove(peer_id.trim());
+        persist_peer_acl(&acl);
+        info!("Untrusted peer: {}", peer_id.trim());
+    }
+}
+
+async fn handle_list_blocked() {
+    let acl = PEER_ACL.lock().expect("lock not poisoned");
+    info!("Blocked peers ({})", acl.blocked.len());
+    acl.blocked.iter().for_each(|p| info!("{}", p));
+}
+
+/// Toggles allowlist-only mode, where only explicitly trusted peers get responses.
+async fn handle_allowlist(enabled: bool) {
+    let mut acl = PEER_ACL.lock().expect("lock not poisoned");
+    acl.allowlist_only = enabled;
+    persist_peer_acl(&acl);
+    info!("Allowlist-only mode: {}", enabled);
+}
+
 async fn handle_create_book_review(cmd: &str) {
     if let Some(rest) = cmd.strip_prefix("create review") {
         let elements: Vec<&str> = rest.split("|").collect();
@@ -334,9 +1138,14 @@ async fn handle_create_book_review(cmd: &str) {
             let author = elements.get(2).expect("author is there");
             let rating = elements.get(3).expect("rating is there");
             let review = elements.get(4).expect("review is there");
-            if let Err(e) = create_new_book(title, genre, author, rating, review).await {
-                error!("error creating book review: {}", e);
-            };
+            match rating.trim().parse::<u8>() {
+                Ok(rating) => {
+                    if let Err(e) = create_new_book(title, genre, author, rating, review).await {
+                        error!("error creating book review: {}", e);
+                    };
+                }
+                Err(e) => error!("invalid rating {}: {}", rating, e),
+            }
         }
     }
 }
@@ -371,4 +1180,4 @@ async fn handle_publish_book_review(cmd: &str) {
             Err(e) => error!("invalid id: {}, {}", rest.trim(), e),
         };
     }
-}
\ No newline at end of file
+}